@@ -0,0 +1,165 @@
+use byteorder::{ByteOrder, NetworkEndian};
+use info::InetAddr;
+
+// UDP's protocol number in the IPv4/IPv6 pseudo-header, RFC768/RFC8200.
+const UDP_PROTOCOL: u8 = 17;
+
+// The RFC1071 one's-complement "Internet checksum" used by UDP, which is
+// what GTP-U rides on. Bytes are fed in incrementally so the pseudo-header,
+// the UDP header and the GTP payload can each be added as they become
+// available, without having to assemble one contiguous buffer first.
+pub struct Checksum {
+    sum: u32,
+}
+
+impl Checksum {
+    pub fn new() -> Self {
+        Checksum { sum: 0 }
+    }
+
+    // Seeds the accumulator with the IPv4/IPv6 pseudo-header: source and
+    // destination address, the UDP protocol number, and the UDP length.
+    // This is exactly the data `GtpPeerAddr` already hands us as an
+    // `InetAddr`. The trailing layout differs by address family: RFC768
+    // packs a zero byte, the protocol number and a 16-bit length after the
+    // addresses, while RFC8200 packs a 32-bit length followed by three
+    // zero bytes and the next-header byte. `src` and `dst` are assumed to
+    // be the same family, as they always are for a single UDP datagram.
+    pub fn with_pseudo_header<'a>(src: &InetAddr<'a>, dst: &InetAddr<'a>, udp_len: u16) -> Self {
+        let mut cs = Checksum::new();
+        cs.add_addr(src);
+        cs.add_addr(dst);
+        match *src {
+            InetAddr::V4(_) => {
+                cs.add_bytes(&[0, UDP_PROTOCOL]);
+                let mut len_buf = [0; 2];
+                NetworkEndian::write_u16(&mut len_buf, udp_len);
+                cs.add_bytes(&len_buf);
+            }
+            InetAddr::V6(_) => {
+                let mut len_buf = [0; 4];
+                NetworkEndian::write_u32(&mut len_buf, udp_len as u32);
+                cs.add_bytes(&len_buf);
+                cs.add_bytes(&[0, 0, 0, UDP_PROTOCOL]);
+            }
+        }
+        cs
+    }
+
+    fn add_addr<'a>(&mut self, addr: &InetAddr<'a>) {
+        match *addr {
+            InetAddr::V4(ip) => {
+                let mut buf = [0; 4];
+                NetworkEndian::write_u32(&mut buf, ip);
+                self.add_bytes(&buf);
+            }
+            InetAddr::V6(ref bytes) => self.add_bytes(bytes),
+        }
+    }
+
+    // Sums successive 16-bit big-endian words, padding a trailing odd byte
+    // with a zero.
+    pub fn add_bytes(&mut self, bytes: &[u8]) {
+        for word in bytes.chunks(2) {
+            let hi = word[0] as u32;
+            let lo = *word.get(1).unwrap_or(&0) as u32;
+            self.sum += (hi << 8) | lo;
+        }
+    }
+
+    // Folds the carries into the low 16 bits and returns the one's
+    // complement, i.e. the value a valid UDP Checksum field must hold.
+    pub fn finish(mut self) -> u16 {
+        while (self.sum >> 16) != 0 {
+            self.sum = (self.sum >> 16) + (self.sum & 0xFFFF);
+        }
+        !(self.sum as u16)
+    }
+}
+
+// Computes the checksum to store in a UDP header's Checksum field before
+// sending a UDP/GTP-U datagram.
+pub fn udp_checksum<'a>(src: &InetAddr<'a>,
+                         dst: &InetAddr<'a>,
+                         udp_header: &[u8],
+                         payload: &[u8]) -> u16 {
+    checksum_datagram(src, dst, udp_header, payload).finish()
+}
+
+// Validates the UDP checksum already present in `udp_header` against the
+// pseudo-header and the GTP `payload` that follows it.
+pub fn verify_udp_checksum<'a>(src: &InetAddr<'a>,
+                                dst: &InetAddr<'a>,
+                                udp_header: &[u8],
+                                payload: &[u8]) -> bool {
+    checksum_datagram(src, dst, udp_header, payload).finish() == 0
+}
+
+fn checksum_datagram<'a>(src: &InetAddr<'a>,
+                          dst: &InetAddr<'a>,
+                          udp_header: &[u8],
+                          payload: &[u8]) -> Checksum {
+    let udp_len = (udp_header.len() + payload.len()) as u16;
+    let mut cs = Checksum::with_pseudo_header(src, dst, udp_len);
+    cs.add_bytes(udp_header);
+    cs.add_bytes(payload);
+    cs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksum_of_known_bytes() {
+        // RFC1071, section 3.
+        let mut cs = Checksum::new();
+        cs.add_bytes(&[0x00, 0x01, 0xf2, 0x03, 0xf4, 0xf5, 0xf6, 0xf7]);
+        assert_eq!(cs.finish(), 0x220d);
+    }
+
+    #[test]
+    fn verify_accepts_a_checksum_it_generated() {
+        let src = InetAddr::V4(0x0a000001);
+        let dst = InetAddr::V4(0x0a000002);
+        let payload = [1, 2, 3, 4];
+        let mut udp_header = [0, 53, 0, 80, 0, 12, 0, 0];
+
+        let computed = udp_checksum(&src, &dst, &udp_header, &payload);
+        udp_header[6] = (computed >> 8) as u8;
+        udp_header[7] = computed as u8;
+
+        assert!(verify_udp_checksum(&src, &dst, &udp_header, &payload));
+    }
+
+    #[test]
+    fn verify_rejects_a_corrupted_payload() {
+        let src = InetAddr::V4(0x0a000001);
+        let dst = InetAddr::V4(0x0a000002);
+        let payload = [1, 2, 3, 4];
+        let mut udp_header = [0, 53, 0, 80, 0, 12, 0, 0];
+
+        let computed = udp_checksum(&src, &dst, &udp_header, &payload);
+        udp_header[6] = (computed >> 8) as u8;
+        udp_header[7] = computed as u8;
+
+        let corrupted = [1, 2, 3, 5];
+        assert!(!verify_udp_checksum(&src, &dst, &udp_header, &corrupted));
+    }
+
+    #[test]
+    fn verify_accepts_a_v6_checksum_it_generated() {
+        let src_bytes = [0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+        let dst_bytes = [0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2];
+        let src = InetAddr::V6(Box::new(&src_bytes));
+        let dst = InetAddr::V6(Box::new(&dst_bytes));
+        let payload = [1, 2, 3, 4];
+        let mut udp_header = [0, 53, 0, 80, 0, 12, 0, 0];
+
+        let computed = udp_checksum(&src, &dst, &udp_header, &payload);
+        udp_header[6] = (computed >> 8) as u8;
+        udp_header[7] = computed as u8;
+
+        assert!(verify_udp_checksum(&src, &dst, &udp_header, &payload));
+    }
+}
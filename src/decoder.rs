@@ -0,0 +1,127 @@
+use byteorder::{ByteOrder, NetworkEndian};
+use header::Gtp;
+use parser::{Parser, ParseResult};
+
+// The fixed part of the header (flags, message type, length, TEID) that is
+// always present and that carries the Length field needed to frame the
+// rest of the packet. See TS29281, 5.1.
+const MANDATORY_HEADER_LEN: usize = 8;
+
+// Reframes a byte stream into individual GTP packets. `Gtp::parse` needs a
+// complete packet up front, but GTP-U traffic read off a UDP socket or a
+// pcap stream arrives in arbitrary chunks, so this buffers partial input,
+// waits for the mandatory header to learn the declared Length, and only
+// then hands a bounded slice to `Gtp::parse`, one packet at a time.
+pub struct Decoder {
+    buf: Vec<u8>,
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Decoder { buf: Vec::new() }
+    }
+
+    // Buffers `data` and invokes `f` with every packet that can now be
+    // framed off the accumulated bytes. Any trailing partial packet is
+    // retained for the next call to `push`.
+    pub fn push<F: FnMut(ParseResult<Gtp>)>(&mut self, data: &[u8], mut f: F) {
+        self.buf.extend_from_slice(data);
+
+        let mut consumed = 0;
+        while let Some(len) = Self::peek_length(&self.buf[consumed..]) {
+            let total = MANDATORY_HEADER_LEN + len;
+            if self.buf.len() - consumed < total {
+                break;
+            }
+            let frame = &self.buf[consumed..consumed + total];
+            let mut p = Parser::new(frame);
+            f(Gtp::parse(&mut p));
+            consumed += total;
+        }
+
+        self.buf.drain(0..consumed);
+    }
+
+    // Reads the Length field out of the mandatory header, if enough bytes
+    // have arrived to see it.
+    fn peek_length(buf: &[u8]) -> Option<usize> {
+        if buf.len() < MANDATORY_HEADER_LEN {
+            return None;
+        }
+        Some(NetworkEndian::read_u16(&buf[2..4]) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A minimal header with an empty body, and one carrying a single
+    // Recovery IE, matching the fixtures used in header.rs's own tests.
+    const MINIMAL: [u8; 8] = [0b00110000, 1, 0, 0, 0, 0, 0, 1];
+    const WITH_BODY: [u8; 10] = [0b00110000, 1, 0, 2, 0, 0, 0, 1, 14, 7];
+
+    #[test]
+    fn packet_split_mid_header() {
+        let mut decoder = Decoder::new();
+        let mut seen = 0;
+        decoder.push(&MINIMAL[..3], |_| seen += 1);
+        assert_eq!(seen, 0);
+
+        decoder.push(&MINIMAL[3..], |r| {
+            assert!(r.is_ok());
+            seen += 1;
+        });
+        assert_eq!(seen, 1);
+    }
+
+    #[test]
+    fn packet_split_mid_body() {
+        let mut decoder = Decoder::new();
+        let mut seen = 0;
+        decoder.push(&WITH_BODY[..9], |_| seen += 1);
+        assert_eq!(seen, 0);
+
+        decoder.push(&WITH_BODY[9..], |r| {
+            assert!(r.is_ok());
+            seen += 1;
+        });
+        assert_eq!(seen, 1);
+    }
+
+    #[test]
+    fn multiple_packets_in_one_push() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&MINIMAL);
+        data.extend_from_slice(&WITH_BODY);
+
+        let mut decoder = Decoder::new();
+        let mut seen = 0;
+        decoder.push(&data, |r| {
+            assert!(r.is_ok());
+            seen += 1;
+        });
+        assert_eq!(seen, 2);
+    }
+
+    #[test]
+    fn trailing_partial_packet_retained_across_pushes() {
+        let mut first = Vec::new();
+        first.extend_from_slice(&MINIMAL);
+        first.extend_from_slice(&WITH_BODY[..4]);
+
+        let mut decoder = Decoder::new();
+        let mut seen = 0;
+        decoder.push(&first, |r| {
+            assert!(r.is_ok());
+            seen += 1;
+        });
+        assert_eq!(seen, 1);
+
+        decoder.push(&WITH_BODY[4..], |r| {
+            assert!(r.is_ok());
+            seen += 1;
+        });
+        assert_eq!(seen, 2);
+    }
+}
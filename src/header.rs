@@ -1,8 +1,13 @@
 use info::InfoElement;
-use parser::{Parser, ParseError, ParseResult};
+use parser::{Parser, ParseError, ParseResult, Serializer};
+use std::cmp::Ordering;
 use std::collections::hash_set::{HashSet};
+use std::ops::{Add, AddAssign, Sub};
+
+// The spare bit of the first octet (TS29281, 5.1), which carries no
+// information but shall be sent as 1.
+const SPARE_BIT: u8 = 0b00010000;
 
-// TODO: Add message type too.
 // TODO: This started out as a direct translation of the protocol type,
 //       but some of the fields are likely only required for parsing,
 //       e.g. flags and length and can be removed.
@@ -11,35 +16,70 @@ pub struct Gtp<'a> {
     pub version: Version,
     pub protocol: Protocol,
     pub flags: Flags,
+    pub msg_type: MessageType,
     pub length: Length,
     pub teid: TunnelEid,
     pub seq_num: Option<SequenceNumber>,
     pub npdu_num: Option<NPduNumber>,
     pub ext_hdrs: Vec<ExtensionHeader<'a>>,
+    pub body: Vec<InfoElement<'a>>,
 }
 
 impl<'a> Gtp<'a> {
     pub fn parse(p: &mut Parser<'a>) -> ParseResult<Gtp<'a>> {
-        let top   = p.parse_u8()?;
-        let ver   = Version::parse(top)?;
-        let proto = Protocol::parse(top)?;
-        let flags = Flags::parse(top)?;
-        let len   = Length::parse(p)?;
-        let teid  = TunnelEid::parse(p)?;
+        let top      = p.parse_u8()?;
+        let ver      = Version::parse(top)?;
+        let proto    = Protocol::parse(top)?;
+        let flags    = Flags::parse(top)?;
+        let msg_type = MessageType::parse(p.parse_u8()?)?;
+        let len      = Length::parse(p)?;
+        let teid     = TunnelEid::parse(p)?;
+        let after_mandatory = p.pos();
         let seq_num = flags.parse_seq_num(p)?;
         let npdu_num = flags.parse_npdu(p)?;
         let ext_hdrs = flags.parse_ext_hdrs(p)?;
+        let optional_len = p.pos() - after_mandatory;
+        let body_len = (len.0 as usize).checked_sub(optional_len)
+            .ok_or(ParseError::PrematureEnd)?;
+        let body = InfoElement::parse_all(p, body_len)?;
         Ok(Gtp {
             version: ver,
             protocol: proto,
             flags: flags,
+            msg_type: msg_type,
             length: len,
             teid: teid,
             seq_num: seq_num,
             npdu_num: npdu_num,
-            ext_hdrs: ext_hdrs
+            ext_hdrs: ext_hdrs,
+            body: body
         })
     }
+
+    pub fn write(&self, out: &mut Serializer) {
+        let mut payload = Serializer::new();
+        if let Some(ref seq_num) = self.seq_num {
+            seq_num.write(&mut payload);
+        }
+        if let Some(ref npdu_num) = self.npdu_num {
+            npdu_num.write(&mut payload);
+        }
+        if !self.ext_hdrs.is_empty() {
+            ExtensionHeader::write(&self.ext_hdrs, &mut payload);
+        }
+        for ie in &self.body {
+            ie.write(&mut payload);
+        }
+
+        let flags = Flags::from_presence(self.seq_num.is_some(),
+                                          self.npdu_num.is_some(),
+                                          !self.ext_hdrs.is_empty());
+        out.write_u8(self.version.write() | self.protocol.write() | flags.write() | SPARE_BIT);
+        self.msg_type.write(out);
+        out.write_u16(payload.len() as u16);
+        self.teid.write(out);
+        out.write_bytes(payload.as_slice());
+    }
 }
 
 #[derive(Eq, Debug, PartialEq)]
@@ -49,6 +89,10 @@ impl Version {
     pub fn parse(b: u8) -> ParseResult<Version>{
         Ok(Version(b >> 5))
     }
+
+    pub fn write(&self) -> u8 {
+        self.0 << 5
+    }
 }
 
 #[derive(Debug)]
@@ -64,6 +108,13 @@ impl Protocol {
             _ => Ok(Protocol::Gtp),
         }
     }
+
+    pub fn write(&self) -> u8 {
+        match *self {
+            Protocol::Gtp => 0b00100000,
+            Protocol::GtpPrime => 0,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -78,6 +129,22 @@ impl Flags {
         Ok(Flags(res))
     }
 
+    fn from_presence(seq_num: bool, npdu_num: bool, ext_hdrs: bool) -> Self {
+        let mut res = HashSet::new();
+        if npdu_num { res.insert(Flag::NPduNumber); }
+        if seq_num { res.insert(Flag::SequenceNumber); }
+        if ext_hdrs { res.insert(Flag::ExtensionHeader); }
+        Flags(res)
+    }
+
+    pub fn write(&self) -> u8 {
+        let mut b = 0;
+        if self.contains(&Flag::NPduNumber) { b |= 0b00000001; }
+        if self.contains(&Flag::SequenceNumber) { b |= 0b00000010; }
+        if self.contains(&Flag::ExtensionHeader) { b |= 0b00000100; }
+        b
+    }
+
     fn parse_seq_num(&self, p: &mut Parser)
                      -> ParseResult<Option<SequenceNumber>> {
         if self.contains(&Flag::SequenceNumber) {
@@ -134,10 +201,39 @@ impl Flag {
     }
 }
 
+#[derive(Debug, Eq, PartialEq)]
 pub enum MessageType {
-    EchoRequest,   // TS29281, 7.2.1
-    EchoResponse,  // TS29281, 7.2.2
+    EchoRequest,                            // 1,   TS29281, 7.2.1
+    EchoResponse,                           // 2,   TS29281, 7.2.2
+    ErrorIndication,                         // 26,  TS29281, 7.3.1
+    SupportedExtensionHeadersNotification,  // 31,  TS29281, 7.3.2
+    EndMarker,                               // 254, TS29281, 7.2.5
+    GPdu,                                    // 255, TS29281, 7.2.6
+}
 
+impl MessageType {
+    pub fn parse(b: u8) -> ParseResult<Self> {
+        match b {
+            1   => Ok(MessageType::EchoRequest),
+            2   => Ok(MessageType::EchoResponse),
+            26  => Ok(MessageType::ErrorIndication),
+            31  => Ok(MessageType::SupportedExtensionHeadersNotification),
+            254 => Ok(MessageType::EndMarker),
+            255 => Ok(MessageType::GPdu),
+            _   => Err(ParseError::UnsupportedMessageType(b))
+        }
+    }
+
+    pub fn write(&self, out: &mut Serializer) {
+        out.write_u8(match *self {
+            MessageType::EchoRequest => 1,
+            MessageType::EchoResponse => 2,
+            MessageType::ErrorIndication => 26,
+            MessageType::SupportedExtensionHeadersNotification => 31,
+            MessageType::EndMarker => 254,
+            MessageType::GPdu => 255,
+        });
+    }
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -156,6 +252,10 @@ impl TunnelEid {
     pub fn parse(p: &mut Parser) -> ParseResult<Self> {
         p.parse_u32().map(TunnelEid)
     }
+
+    pub fn write(&self, out: &mut Serializer) {
+        out.write_u32(self.0);
+    }
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -165,6 +265,61 @@ impl SequenceNumber {
     pub fn parse(p: &mut Parser) -> ParseResult<Self> {
         p.parse_u16().map(SequenceNumber)
     }
+
+    pub fn write(&self, out: &mut Serializer) {
+        out.write_u16(self.0);
+    }
+}
+
+// Sequence numbers are a cyclic counter (TS29281, 7.6): arithmetic wraps at
+// 2^16, and "newer than" is determined by the sign of the wrapping
+// difference, the same way TCP sequence numbers are compared.
+impl Add<u16> for SequenceNumber {
+    type Output = SequenceNumber;
+
+    fn add(self, rhs: u16) -> SequenceNumber {
+        SequenceNumber(self.0.wrapping_add(rhs))
+    }
+}
+
+impl Sub<u16> for SequenceNumber {
+    type Output = SequenceNumber;
+
+    fn sub(self, rhs: u16) -> SequenceNumber {
+        SequenceNumber(self.0.wrapping_sub(rhs))
+    }
+}
+
+impl AddAssign<u16> for SequenceNumber {
+    fn add_assign(&mut self, rhs: u16) {
+        self.0 = self.0.wrapping_add(rhs);
+    }
+}
+
+// Forward distance from `rhs` to `self`, i.e. how many increments of `rhs`
+// reach `self` when walking forward around the cycle.
+impl Sub<SequenceNumber> for SequenceNumber {
+    type Output = u16;
+
+    fn sub(self, rhs: SequenceNumber) -> u16 {
+        self.0.wrapping_sub(rhs.0)
+    }
+}
+
+impl PartialOrd for SequenceNumber {
+    fn partial_cmp(&self, other: &SequenceNumber) -> Option<Ordering> {
+        let d = self.0.wrapping_sub(other.0) as i16;
+        if d == 0 {
+            Some(Ordering::Equal)
+        } else if d == i16::min_value() {
+            // Exactly half a cycle apart: direction is ambiguous.
+            None
+        } else if d < 0 {
+            Some(Ordering::Less)
+        } else {
+            Some(Ordering::Greater)
+        }
+    }
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -174,6 +329,10 @@ impl NPduNumber {
     pub fn parse(p: &mut Parser) -> ParseResult<Self> {
         p.parse_u8().map(NPduNumber)
     }
+
+    pub fn write(&self, out: &mut Serializer) {
+        out.write_u8(self.0);
+    }
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -201,6 +360,18 @@ impl ExtHeaderType {
             _          => Err(ParseError::UnsupportedExtensionHeader(t))
         }
     }
+
+    pub fn write(&self, out: &mut Serializer) {
+        out.write_u8(match *self {
+            ExtHeaderType::EndReached => 0b00000000,
+            ExtHeaderType::MbmsSupport => 0b00000001,
+            ExtHeaderType::MsInfoChangeReporting => 0b00000010,
+            ExtHeaderType::UdpPort => 0b01000000,
+            ExtHeaderType::PdcpPdu => 0b11000000,
+            ExtHeaderType::SuspendRequest => 0b11000001,
+            ExtHeaderType::SuspendResponse => 0b11000010,
+        });
+    }
 }
 
 #[derive(Debug)]
@@ -230,32 +401,154 @@ impl<'a> ExtensionHeader<'a> {
         }
         Ok(())
     }
+
+    pub fn write(hdrs: &[ExtensionHeader<'a>], out: &mut Serializer) {
+        if let Some((first, rest)) = hdrs.split_first() {
+            first.kind.write(out);
+            ExtensionHeader::write_one(first, rest, out);
+        }
+    }
+
+    fn write_one(hdr: &ExtensionHeader<'a>,
+                 rest: &[ExtensionHeader<'a>],
+                 out: &mut Serializer) {
+        out.write_u8((hdr.content.len() / 4) as u8);
+        out.write_bytes(hdr.content);
+        match rest.split_first() {
+            Some((next, tail)) => {
+                next.kind.write(out);
+                ExtensionHeader::write_one(next, tail, out);
+            }
+            None => ExtHeaderType::EndReached.write(out),
+        }
+    }
 }
 
 
 #[cfg(test)]
 mod tests {
-    use parser::Parser;
+    use info::InetAddr;
+    use parser::{Parser, Serializer};
     use super::*;
 
     #[test]
     fn parse_minimal_header() {
-        let raw = [0b00110000, 0, 0, 1, 0, 0, 0, 0];
+        let raw = [0b00110000, 1, 0, 0, 0, 0, 0, 1];
         let mut p = Parser::new(&raw);
         let parsed = Gtp::parse(&mut p).unwrap();
         assert!(parsed.flags.0.is_empty());
         assert_eq!(parsed.version, Version(1));
+        assert_eq!(parsed.msg_type, MessageType::EchoRequest);
         assert_eq!(parsed.length, Length(0));
         assert_eq!(parsed.teid, TunnelEid(1));
+        assert!(parsed.body.is_empty());
+    }
+
+    // TEID 0x00000001 on the wire, per TS29281 network byte order, not
+    // the little-endian encoding a naive `u32::from_le_bytes` would expect.
+    #[test]
+    fn teid_is_decoded_big_endian() {
+        let raw = [0b00110000, 1, 0, 0, 0x00, 0x00, 0x00, 0x01];
+        let mut p = Parser::new(&raw);
+        let parsed = Gtp::parse(&mut p).unwrap();
+        assert_eq!(parsed.teid, TunnelEid(1));
     }
 
     #[test]
     fn parse_basic_header() {
-        let raw = [0b00110011, 0, 0, 1, 0, 0, 0, 14, 0, 5, 0];
+        let raw = [0b00110011, 1, 0, 3, 0, 0, 0, 1, 0, 14, 5];
         let mut p = Parser::new(&raw);
         let parsed = Gtp::parse(&mut p).unwrap();
         assert!(!parsed.flags.0.is_empty());
         assert_eq!(parsed.seq_num, Some(SequenceNumber(14)));
         assert_eq!(parsed.npdu_num, Some(NPduNumber(5)));
+        assert!(parsed.body.is_empty());
+    }
+
+    #[test]
+    fn parse_header_with_body() {
+        let raw = [0b00110000, 1, 0, 2, 0, 0, 0, 1, 14, 7];
+        let mut p = Parser::new(&raw);
+        let parsed = Gtp::parse(&mut p).unwrap();
+        assert_eq!(parsed.body.len(), 1);
+        match parsed.body[0] {
+            InfoElement::Recovery(_) => {},
+            _ => panic!("expected a Recovery IE"),
+        }
+    }
+
+    #[test]
+    fn parse_header_with_peer_addr_body() {
+        let raw = [0b00110000, 1, 0, 6, 0, 0, 0, 1, 0b10000101, 4, 10, 0, 0, 1];
+        let mut p = Parser::new(&raw);
+        let parsed = Gtp::parse(&mut p).unwrap();
+        assert_eq!(parsed.body.len(), 1);
+        match parsed.body[0] {
+            InfoElement::GtpPeerAddr(InetAddr::V4(addr)) => assert_eq!(addr, 0x0a000001),
+            _ => panic!("expected a GtpPeerAddr IE"),
+        }
+    }
+
+    #[test]
+    fn round_trip_header_with_peer_addr_body() {
+        let raw = [0b00110000, 1, 0, 6, 0, 0, 0, 1, 0b10000101, 4, 10, 0, 0, 1];
+        let mut p = Parser::new(&raw);
+        let parsed = Gtp::parse(&mut p).unwrap();
+        let mut out = Serializer::new();
+        parsed.write(&mut out);
+        assert_eq!(out.into_bytes(), raw.to_vec());
+    }
+
+    #[test]
+    fn round_trip_minimal_header() {
+        let raw = [0b00110000, 1, 0, 0, 0, 0, 0, 1];
+        let mut p = Parser::new(&raw);
+        let parsed = Gtp::parse(&mut p).unwrap();
+        let mut out = Serializer::new();
+        parsed.write(&mut out);
+        assert_eq!(out.into_bytes(), raw.to_vec());
+    }
+
+    #[test]
+    fn sequence_number_wraps() {
+        assert_eq!(SequenceNumber(0xFFFF) + 1, SequenceNumber(0));
+        assert_eq!(SequenceNumber(0) - 1, SequenceNumber(0xFFFF));
+    }
+
+    #[test]
+    fn sequence_number_ordering_across_wraparound() {
+        assert!(SequenceNumber(1) > SequenceNumber(0));
+        assert!(SequenceNumber(0) < SequenceNumber(1));
+        assert!(SequenceNumber(0) < SequenceNumber(0xFFFF) + 2);
+        assert!(SequenceNumber(0xFFFF) < SequenceNumber(0));
+        assert!(SequenceNumber(0) > SequenceNumber(0xFFFF));
+        assert_eq!(SequenceNumber(5).partial_cmp(&SequenceNumber(5)), Some(Ordering::Equal));
+        assert_eq!(SequenceNumber(0).partial_cmp(&SequenceNumber(0x8000)), None);
+    }
+
+    #[test]
+    fn sequence_number_distance() {
+        assert_eq!(SequenceNumber(10) - SequenceNumber(4), 6);
+        assert_eq!(SequenceNumber(2) - SequenceNumber(0xFFFE), 4);
+    }
+
+    #[test]
+    fn round_trip_basic_header() {
+        let raw = [0b00110011, 1, 0, 3, 0, 0, 0, 1, 0, 14, 5];
+        let mut p = Parser::new(&raw);
+        let parsed = Gtp::parse(&mut p).unwrap();
+        let mut out = Serializer::new();
+        parsed.write(&mut out);
+        assert_eq!(out.into_bytes(), raw.to_vec());
+    }
+
+    #[test]
+    fn round_trip_header_with_body() {
+        let raw = [0b00110000, 1, 0, 2, 0, 0, 0, 1, 14, 7];
+        let mut p = Parser::new(&raw);
+        let parsed = Gtp::parse(&mut p).unwrap();
+        let mut out = Serializer::new();
+        parsed.write(&mut out);
+        assert_eq!(out.into_bytes(), raw.to_vec());
     }
 }
@@ -1,6 +1,7 @@
-use byteorder::{ByteOrder, LittleEndian};
-use parser::{Parser, ParseError, ParseResult};
+use byteorder::{ByteOrder, NetworkEndian};
+use parser::{Parser, ParseError, ParseResult, Serializer};
 
+#[derive(Debug)]
 pub enum InfoElement<'a> {
     // 14, TS29281, 8.2
     // The Restart Counter value is unused and shall be zeroed/ignored.
@@ -21,15 +22,26 @@ pub enum InfoElement<'a> {
 }
 
 impl<'a> InfoElement<'a> {
-    pub fn parse(p: &'a mut Parser<'a>) -> ParseResult<Self> {
+    pub fn parse(p: &mut Parser<'a>) -> ParseResult<Self> {
         let ie_type = p.parse_u8()?;
-        if ie_type & 0b1000000 == 0 {
+        if ie_type & 0b10000000 == 0 {
             Self::parse_fixed(ie_type, p)
         } else {
-            Self::parse_variable(ie_type & 0b01111111, p)
+            Self::parse_variable(ie_type, p)
         }
     }
 
+    // Parses `InfoElement`s off `p` until `len` bytes of body have been
+    // consumed, as declared by the enclosing header's Length field.
+    pub fn parse_all(p: &mut Parser<'a>, len: usize) -> ParseResult<Vec<Self>> {
+        let start = p.pos();
+        let mut res = Vec::new();
+        while p.pos() - start < len {
+            res.push(InfoElement::parse(p)?);
+        }
+        Ok(res)
+    }
+
     fn parse_fixed(ie_type: u8, p: &mut Parser) -> ParseResult<Self> {
         match ie_type {
             14 => RestartCounter::parse(p).map(InfoElement::Recovery),
@@ -38,29 +50,60 @@ impl<'a> InfoElement<'a> {
         }
     }
 
-    fn parse_variable(ie_type: u8, p: &'a mut Parser<'a>) -> ParseResult<Self> {
+    fn parse_variable(ie_type: u8, p: &mut Parser<'a>) -> ParseResult<Self> {
         let len = p.parse_u8()?;
         match ie_type {
             133 => InetAddr::parse(len, p).map(InfoElement::GtpPeerAddr),
             _   => Err(ParseError::UnsupportedInformationElement(ie_type))
         }
     }
+
+    pub fn write(&self, out: &mut Serializer) {
+        match *self {
+            InfoElement::Recovery(ref rc) => {
+                out.write_u8(14);
+                rc.write(out);
+            }
+            InfoElement::TeiData(ref td) => {
+                out.write_u8(16);
+                td.write(out);
+            }
+            InfoElement::GtpPeerAddr(ref addr) => {
+                out.write_u8(133);
+                addr.write(out);
+            }
+            InfoElement::ExtHeader(ref eh) => {
+                out.write_u8(141);
+                eh.write(out);
+            }
+        }
+    }
 }
 
+#[derive(Debug)]
 pub struct RestartCounter(u8);
 
 impl RestartCounter {
     pub fn parse(p: &mut Parser) -> ParseResult<Self> {
         p.parse_u8().map(RestartCounter)
     }
+
+    pub fn write(&self, out: &mut Serializer) {
+        out.write_u8(self.0);
+    }
 }
 
+#[derive(Debug)]
 pub struct TeiData(u32);
 
 impl TeiData {
     pub fn parse(p: &mut Parser) -> ParseResult<Self> {
         p.parse_u32().map(TeiData)
     }
+
+    pub fn write(&self, out: &mut Serializer) {
+        out.write_u32(self.0);
+    }
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -73,6 +116,7 @@ impl Length {
 }
 
 // TS29281, 5.2
+#[derive(Debug)]
 pub struct ExtHeader {
     pub comprehension: Comprehension,
     pub header: ExtType
@@ -91,9 +135,15 @@ impl ExtHeader {
             header: etype,
         })
     }
+
+    pub fn write(&self, out: &mut Serializer) {
+        out.write_u8(self.header.type_bits() | self.comprehension.write());
+        self.header.write(out);
+    }
 }
 
 // TS29281, 5.2.2
+#[derive(Debug)]
 pub enum ExtType {
     UdpPort(u16),
     PdcpPduNumber(u32),
@@ -110,16 +160,39 @@ impl ExtType {
     }
 
     fn parse_udp_port(len: u8, p: &mut Parser) -> ParseResult<Self> {
-        let port = p.parse(len as usize * 4).map(LittleEndian::read_u32)?;
-        if port > 2^16 {
+        let port = p.parse(len as usize * 4).map(NetworkEndian::read_u32)?;
+        if port > 1u32 << 16 {
             Err(ParseError::BadUdpPort(port))
         } else {
             Ok(ExtType::UdpPort(port as u16))
         }
     }
+
+    // The top two bits of the extension header's type octet, shared with
+    // the `Comprehension` encoding; see `ExtType::parse`.
+    fn type_bits(&self) -> u8 {
+        match *self {
+            ExtType::UdpPort(_) => 0b01000000,
+            ExtType::PdcpPduNumber(_) => 0b11000000,
+        }
+    }
+
+    fn write(&self, out: &mut Serializer) {
+        match *self {
+            ExtType::UdpPort(port) => {
+                out.write_u8(1);
+                out.write_u32(port as u32);
+            }
+            ExtType::PdcpPduNumber(n) => {
+                out.write_u8(1);
+                out.write_u32(n);
+            }
+        }
+    }
 }
 
 // TS29281, 5.2.1
+#[derive(Debug)]
 pub enum Comprehension {
     Optional,      // Forward unknown headers
     Discard,       // Discard unknown haders
@@ -136,15 +209,25 @@ impl Comprehension {
             (true, true)   => Comprehension::Unconditional
         })
     }
+
+    fn write(&self) -> u8 {
+        match *self {
+            Comprehension::Optional => 0b00000000,
+            Comprehension::Discard => 0b01000000,
+            Comprehension::Receiver => 0b10000000,
+            Comprehension::Unconditional => 0b11000000,
+        }
+    }
 }
 
+#[derive(Debug)]
 pub enum InetAddr<'a> {
     V4(u32),
     V6(Box<&'a [u8]>)
 }
 
 impl<'a> InetAddr<'a> {
-    fn parse(len: u8, p: &'a mut Parser<'a>) -> ParseResult<Self> {
+    fn parse(len: u8, p: &mut Parser<'a>) -> ParseResult<Self> {
         match len {
             4  => Self::parse_v4(p),
             16 => Self::parse_v6(p),
@@ -153,12 +236,25 @@ impl<'a> InetAddr<'a> {
     }
 
     fn parse_v4(p: &mut Parser<'a>) -> ParseResult<Self> {
-        p.parse(4).map(|s| InetAddr::V4(LittleEndian::read_u32(s)))
+        p.parse(4).map(|s| InetAddr::V4(NetworkEndian::read_u32(s)))
     }
 
     fn parse_v6(p: &mut Parser<'a>) -> ParseResult<Self> {
         p.parse(16).map(|s| InetAddr::V6(Box::new(s)))
     }
+
+    fn write(&self, out: &mut Serializer) {
+        match *self {
+            InetAddr::V4(ip) => {
+                out.write_u8(4);
+                out.write_u32(ip);
+            }
+            InetAddr::V6(ref bytes) => {
+                out.write_u8(16);
+                out.write_bytes(bytes);
+            }
+        }
+    }
 }
 
 pub struct PrivateExt<'a> {
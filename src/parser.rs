@@ -1,4 +1,46 @@
-use byteorder::{ByteOrder, LittleEndian};
+use byteorder::{ByteOrder, NetworkEndian};
+
+pub struct Serializer {
+    bytes: Vec<u8>,
+}
+
+impl Serializer {
+    pub fn new() -> Self {
+        Serializer { bytes: Vec::new() }
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    pub fn write_u8(&mut self, v: u8) {
+        self.bytes.push(v);
+    }
+
+    pub fn write_u16(&mut self, v: u16) {
+        let mut buf = [0; 2];
+        NetworkEndian::write_u16(&mut buf, v);
+        self.write_bytes(&buf);
+    }
+
+    pub fn write_u32(&mut self, v: u32) {
+        let mut buf = [0; 4];
+        NetworkEndian::write_u32(&mut buf, v);
+        self.write_bytes(&buf);
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
 
 pub struct Parser<'a> {
     bytes: &'a [u8],
@@ -11,6 +53,7 @@ pub enum ParseError {
     UnsupportedVersion,
     UnsupportedInformationElement(u8),
     UnsupportedExtensionHeader(u8),
+    UnsupportedMessageType(u8),
     BadIpAddress,
     BadUdpPort(u32),
 }
@@ -22,6 +65,10 @@ impl<'a> Parser<'a> {
         Parser { bytes: bytes, pos: 0 }
     }
 
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
     pub fn parse(&mut self, len: usize) -> ParseResult<&'a [u8]> {
         if self.pos + len > self.bytes.len() {
             return Err(ParseError::PrematureEnd);
@@ -35,10 +82,10 @@ impl<'a> Parser<'a> {
     }
 
     pub fn parse_u16(&mut self) -> ParseResult<u16> {
-        self.parse(2).map(LittleEndian::read_u16)
+        self.parse(2).map(NetworkEndian::read_u16)
     }
 
     pub fn parse_u32(&mut self) -> ParseResult<u32> {
-        self.parse(4).map(LittleEndian::read_u32)
+        self.parse(4).map(NetworkEndian::read_u32)
     }
 }